@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::os::unix::io::AsRawFd;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use sysinfo::{Pid, ProcessExt, System, SystemExt};
+use x11rb::connection::Connection;
+use x11rb::protocol::screensaver::ConnectionExt as ScreensaverConnectionExt;
+use x11rb::protocol::xproto::{self, Atom, ChangeWindowAttributesAux, ConnectionExt, EventMask, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+/// The stable identifier of the currently active application, plus whatever
+/// we could find out about where it's installed.
+#[derive(Debug, Clone)]
+pub struct AppIdentity {
+    /// Stable identifier for the app (WM_CLASS under X11, executable name
+    /// under `/proc`). This is what ends up in the CSV logs.
+    pub app_id: String,
+    /// Path to a `.desktop` file for the app, when one could be found.
+    pub desktop_file: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum SourceError {
+    Xprop(XpropParseError),
+    Proc(String),
+}
+
+/// Something that can tell us which application currently has the user's
+/// attention. Called once per tracking cycle; implementations are expected
+/// to block for up to `timeout` so the main loop's cadence is preserved
+/// even when nothing changes in between. `XpropSource` waits on X11
+/// property-change events; `ProcSource` is the Wayland/headless fallback
+/// and simply sleeps.
+pub trait ActivitySource {
+    fn current_app(&mut self, timeout: Duration) -> Result<AppIdentity, SourceError>;
+
+    /// How long since the last keyboard/mouse input, when the backend is
+    /// able to tell. Backends with no such concept (like `/proc`) report
+    /// `None`, which callers should treat as "never idle".
+    fn idle_time(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum XpropParseError {
+    Connect(String),
+    Atom,
+    WinId,
+    Class,
+}
+
+/// Event-driven X11 backend. Selects `PropertyChangeMask` on the root
+/// window and blocks (with a `poll_interval_secs` timeout) until a root
+/// window property changes, re-reading the active app when
+/// `_NET_ACTIVE_WINDOW` was among the changes, reading `WM_CLASS`/`_BAMF_DESKTOP_FILE`
+/// straight off the wire via `GetProperty` instead of spawning `xprop`.
+pub struct XpropSource {
+    conn: RustConnection,
+    root: Window,
+    net_active_window: Atom,
+    wm_class: Atom,
+    bamf_desktop_file: Atom,
+    last_known: Option<AppIdentity>,
+}
+
+impl XpropSource {
+    pub fn new() -> Result<Self, SourceError> {
+        let (conn, screen_num) = x11rb::connect(None)
+            .map_err(|err| SourceError::Xprop(XpropParseError::Connect(err.to_string())))?;
+        let root = conn.setup().roots[screen_num].root;
+
+        conn.change_window_attributes(
+            root,
+            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+        )
+        .map_err(|_| SourceError::Xprop(XpropParseError::Connect("couldn't select PropertyChangeMask on root window".to_string())))?
+        .check()
+        .map_err(|_| SourceError::Xprop(XpropParseError::Connect("root window is already owned by another screen-time instance".to_string())))?;
+
+        let net_active_window = Self::intern(&conn, b"_NET_ACTIVE_WINDOW")?;
+        let wm_class = Self::intern(&conn, b"WM_CLASS")?;
+        let bamf_desktop_file = Self::intern(&conn, b"_BAMF_DESKTOP_FILE")?;
+
+        Ok(XpropSource {
+            conn,
+            root,
+            net_active_window,
+            wm_class,
+            bamf_desktop_file,
+            last_known: None,
+        })
+    }
+
+    pub fn is_available() -> bool {
+        std::env::var("DISPLAY").is_ok() && x11rb::connect(None).is_ok()
+    }
+
+    fn intern(conn: &RustConnection, name: &[u8]) -> Result<Atom, SourceError> {
+        conn.intern_atom(false, name)
+            .map_err(|_| SourceError::Xprop(XpropParseError::Atom))?
+            .reply()
+            .map(|reply| reply.atom)
+            .map_err(|_| SourceError::Xprop(XpropParseError::Atom))
+    }
+
+    fn active_window(&self) -> Result<Window, SourceError> {
+        let reply = self
+            .conn
+            .get_property(false, self.root, self.net_active_window, xproto::AtomEnum::WINDOW, 0, 1)
+            .map_err(|_| SourceError::Xprop(XpropParseError::WinId))?
+            .reply()
+            .map_err(|_| SourceError::Xprop(XpropParseError::WinId))?;
+
+        reply
+            .value32()
+            .and_then(|mut value| value.next())
+            .ok_or(SourceError::Xprop(XpropParseError::WinId))
+    }
+
+    fn read_identity(&self) -> Result<AppIdentity, SourceError> {
+        let window = self.active_window()?;
+
+        let class_reply = self
+            .conn
+            .get_property(false, window, self.wm_class, xproto::AtomEnum::STRING, 0, 1024)
+            .map_err(|_| SourceError::Xprop(XpropParseError::Class))?
+            .reply()
+            .map_err(|_| SourceError::Xprop(XpropParseError::Class))?;
+
+        //WM_CLASS is two NUL-separated strings, instance then class; keep
+        //using the instance string like the old xprop-based lookup did, so
+        //ignored_apps and existing app-names.csv/log entries still match.
+        let app_id = class_reply
+            .value
+            .split(|&byte| byte == 0)
+            .filter(|part| !part.is_empty())
+            .next()
+            .map(|part| String::from_utf8_lossy(part).to_string())
+            .ok_or(SourceError::Xprop(XpropParseError::Class))?;
+
+        let desktop_file = self
+            .conn
+            .get_property(false, window, self.bamf_desktop_file, xproto::AtomEnum::STRING, 0, 1024)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .filter(|reply| !reply.value.is_empty())
+            .map(|reply| String::from_utf8_lossy(&reply.value).to_string());
+
+        Ok(AppIdentity {
+            app_id,
+            desktop_file,
+        })
+    }
+
+    /// Blocks until the X connection has events to read or `timeout`
+    /// elapses, whichever comes first, then drains the queued events. Any
+    /// root window property change ends the wait, not only
+    /// `_NET_ACTIVE_WINDOW`. Returns whether one of the drained events was a
+    /// `PropertyNotify` for `_NET_ACTIVE_WINDOW`, so the caller knows
+    /// whether to re-read the active window.
+    fn wait_for_focus_change(&mut self, timeout: Duration) -> Result<bool, SourceError> {
+        self.conn
+            .flush()
+            .map_err(|err| SourceError::Xprop(XpropParseError::Connect(err.to_string())))?;
+
+        let fd = self.conn.stream().as_raw_fd();
+        let mut poll_fd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+        let ready = unsafe { libc::poll(&mut poll_fd, 1, millis) };
+        if ready <= 0 {
+            //timed out without a relevant event; the main loop still wants
+            //to run decide()/UpdatePrevious on schedule
+            return Ok(false);
+        }
+
+        let mut changed = false;
+        while let Some(event) = self
+            .conn
+            .poll_for_event()
+            .map_err(|err| SourceError::Xprop(XpropParseError::Connect(err.to_string())))?
+        {
+            if let Event::PropertyNotify(event) = event {
+                if event.atom == self.net_active_window {
+                    changed = true;
+                }
+            }
+        }
+        Ok(changed)
+    }
+}
+
+impl ActivitySource for XpropSource {
+    fn current_app(&mut self, timeout: Duration) -> Result<AppIdentity, SourceError> {
+        let changed = self.wait_for_focus_change(timeout)?;
+
+        if changed || self.last_known.is_none() {
+            self.last_known = Some(self.read_identity()?);
+        }
+
+        Ok(self.last_known.clone().expect("last_known was just populated"))
+    }
+
+    fn idle_time(&self) -> Option<Duration> {
+        let info = self.conn.screensaver_query_info(self.root).ok()?.reply().ok()?;
+        Some(Duration::from_millis(info.ms_since_user_input as u64))
+    }
+}
+
+/// `/proc`-based fallback for Wayland and headless sessions, where there is
+/// no window manager to ask for the focused window. Without that concept we
+/// approximate "active app" as the process currently burning the most CPU,
+/// which is the closest honest proxy `/proc` alone can offer.
+pub struct ProcSource {
+    system: System,
+}
+
+impl ProcSource {
+    pub fn new() -> Self {
+        ProcSource {
+            system: System::new(),
+        }
+    }
+
+    fn process_name(pid: Pid) -> Option<String> {
+        let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|s| s.trim().to_string());
+        if comm.is_some() {
+            return comm;
+        }
+
+        let cmdline = std::fs::read_to_string(format!("/proc/{}/cmdline", pid)).ok()?;
+        cmdline
+            .split('\0')
+            .next()
+            .map(|path| path.rsplit('/').next().unwrap_or(path).to_string())
+    }
+}
+
+impl ActivitySource for ProcSource {
+    fn current_app(&mut self, timeout: Duration) -> Result<AppIdentity, SourceError> {
+        //no event source to block on, so pace ourselves like the old poll loop did
+        std::thread::sleep(timeout);
+
+        self.system.refresh_processes();
+
+        //group multi-process apps (Chrome, Electron, ...) by executable path
+        //first, keeping only the busiest instance of each, so that instance
+        //can still win the overall comparison below
+        let mut busiest_per_exe: HashMap<PathBuf, &sysinfo::Process> = HashMap::new();
+        for process in self.system.processes().values() {
+            busiest_per_exe
+                .entry(process.exe().to_path_buf())
+                .and_modify(|current| {
+                    if process.cpu_usage() > current.cpu_usage() {
+                        *current = process;
+                    }
+                })
+                .or_insert(process);
+        }
+
+        let busiest = busiest_per_exe
+            .values()
+            .copied()
+            .max_by(|left, right| {
+                left.cpu_usage()
+                    .partial_cmp(&right.cpu_usage())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .ok_or_else(|| SourceError::Proc("No running processes found".to_string()))?;
+
+        let app_id = Self::process_name(busiest.pid()).unwrap_or_else(|| busiest.name().to_string());
+
+        Ok(AppIdentity {
+            app_id,
+            desktop_file: None,
+        })
+    }
+}
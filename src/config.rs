@@ -0,0 +1,97 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Tunables for the daemon, loaded once at startup.
+///
+/// Falls back to sane defaults for anything missing from the file (or if the
+/// file itself is absent), so a half-written config never blocks tracking.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// How often the main loop wakes up to check the active window, in seconds.
+    pub poll_interval_secs: u64,
+    /// Gaps shorter than `poll_interval_secs * suspend_gap_multiplier` are
+    /// treated as normal idling; longer gaps are assumed to be a suspend/resume.
+    pub suspend_gap_multiplier: u64,
+    /// Logs older than this many days become eligible for deletion on startup
+    /// and on day rollover; they are deleted only if `keep_count` does not
+    /// also protect them.
+    pub retention_days: i64,
+    /// The `keep_count` most recent log files are never deleted, regardless
+    /// of age. A file is deleted only when it is both older than
+    /// `retention_days` and beyond the `keep_count` most recent files, so
+    /// whichever of the two policies keeps more files wins.
+    pub keep_count: usize,
+    /// App identifiers (WM_CLASS) that should never be tracked.
+    pub ignored_apps: Vec<String>,
+    /// Directory where daily CSV logs and `app-names.csv` are kept.
+    pub data_dir: PathBuf,
+    /// Which `ActivitySource` backend to use: `"xprop"`, `"proc"`, or
+    /// `"auto"` to probe for a working X11 session and fall back to `/proc`.
+    pub source: String,
+    /// Seconds of no keyboard/mouse input after which the current frame is
+    /// closed instead of being extended, so AFK time isn't counted as usage.
+    /// Only enforced by sources that can report idle time (currently `xprop`).
+    pub idle_threshold_secs: u64,
+    /// Minimum level written to `<data_dir>/daemon.log` and, when attached
+    /// to a TTY, stderr. One of `error`, `warn`, `info`, `debug`, `trace`.
+    pub log_level: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut data_dir = dirs::home_dir().expect("Couldn't find your HOME directory");
+        data_dir.push(".screen-time");
+
+        Config {
+            poll_interval_secs: 10,
+            suspend_gap_multiplier: 5,
+            retention_days: 14,
+            keep_count: 365,
+            ignored_apps: vec![
+                "Desktop".to_string(),
+                "unity-panel".to_string(),
+                "wingpanel".to_string(),
+            ],
+            data_dir,
+            source: "auto".to_string(),
+            idle_threshold_secs: 180,
+            log_level: "info".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from `~/.config/screen-time/config.toml`, falling
+    /// back to `Config::default()` when the file is missing or malformed.
+    pub fn load() -> Self {
+        let path = Self::path();
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => return Config::default(),
+        };
+
+        match toml::from_str(&text) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!(
+                    "Error parsing config at {:#?}, falling back to defaults. Reason: {}",
+                    path, err
+                );
+                Config::default()
+            }
+        }
+    }
+
+    fn path() -> PathBuf {
+        let mut path = dirs::config_dir().expect("Couldn't find your config directory");
+        path.push("screen-time");
+        path.push(CONFIG_FILE_NAME);
+        path
+    }
+}
@@ -6,19 +6,50 @@ use std::fs;
 use std::fs::{create_dir, File, OpenOptions};
 
 use std::path::PathBuf;
-use std::process::Command;
 
 use chrono::{Date, Local, NaiveDate};
+use clap::{Parser, Subcommand};
+use log::{debug, error, info, warn, LevelFilter};
+use simplelog::{CombinedLogger, SharedLogger, TermLogger, TerminalMode, WriteLogger};
+
+mod activity_source;
+mod config;
+mod report;
+use activity_source::{ActivitySource, ProcSource, XpropSource};
+use config::Config;
+use report::ReportFormat;
 
 static DELIM: &'static str = ";";
-static TIMEOUT: u64 = 10;
 static DATE_FORMAT: &'static str = "%b-%d-%Y";
 
-#[derive(Debug)]
-enum XpropParseError {
-    WinId,
-    Class,
-    DesktopPath,
+#[derive(Parser)]
+#[command(name = "screen-time-daemon")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+    /// Override the configured log level (error, warn, info, debug, trace)
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Run the tracking daemon (default when no subcommand is given)
+    Track,
+    /// Summarize the logged usage into per-app totals
+    Report {
+        /// Only include logs on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// Only include logs on or before this date (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        /// Only include this app's usage
+        #[arg(long)]
+        app: Option<String>,
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+    },
 }
 
 //activity frame
@@ -33,6 +64,9 @@ pub enum FrameOperation {
     Prepare(Frame),
     WriteNew(Frame),
     UpdatePrevious(u64),
+    //user has gone idle; the frame already on disk ends at last_frame.end,
+    //so there's nothing left to write, just stop extending it
+    Close,
 }
 
 #[derive(Debug)]
@@ -75,69 +109,130 @@ impl CurrentState {
     }
 }
 
+fn select_source(config: &Config) -> Box<dyn ActivitySource> {
+    match config.source.as_str() {
+        "xprop" => Box::new(XpropSource::new().expect("Couldn't connect to the X11 server")),
+        "proc" => Box::new(ProcSource::new()),
+        _ => {
+            if XpropSource::is_available() {
+                Box::new(XpropSource::new().expect("Couldn't connect to the X11 server"))
+            } else {
+                Box::new(ProcSource::new())
+            }
+        }
+    }
+}
+
 fn main() {
-    let mut path_buf = dirs::home_dir().unwrap();
-    path_buf.push(".screen-time");
-    if !path_buf.exists() {
-        create_dir(path_buf.as_path()).expect("Couldn't create .screen-time folder in your HOME");
-    } else {
-        clean_up_old_logs(&path_buf);
+    let cli = Cli::parse();
+    let mut config = Config::load();
+    if let Some(log_level) = cli.log_level {
+        config.log_level = log_level;
+    }
+
+    if !config.data_dir.exists() {
+        create_dir(&config.data_dir).expect("Couldn't create .screen-time folder in your HOME");
+    }
+    init_logging(&config);
+
+    match cli.command.unwrap_or(Commands::Track) {
+        Commands::Track => track(config),
+        Commands::Report {
+            since,
+            until,
+            app,
+            format,
+        } => report::run(&config, since, until, app.as_deref(), format),
+    }
+}
+
+fn init_logging(config: &Config) {
+    let level = config
+        .log_level
+        .parse::<LevelFilter>()
+        .unwrap_or(LevelFilter::Info);
+    let log_path = config.data_dir.join("daemon.log");
+
+    let mut loggers: Vec<Box<dyn SharedLogger>> = Vec::new();
+
+    if atty::is(atty::Stream::Stderr) {
+        loggers.push(TermLogger::new(
+            level,
+            simplelog::Config::default(),
+            TerminalMode::Stderr,
+            simplelog::ColorChoice::Auto,
+        ));
     }
 
+    match OpenOptions::new().create(true).append(true).open(&log_path) {
+        Ok(file) => loggers.push(WriteLogger::new(level, simplelog::Config::default(), file)),
+        Err(err) => eprintln!("Couldn't open log file {:#?}: {}", log_path, err),
+    }
+
+    CombinedLogger::init(loggers).expect("Couldn't initialize logger");
+}
+
+fn track(config: Config) {
+    let mut source = select_source(&config);
+
+    let path_buf = config.data_dir.clone();
+    clean_up_old_logs(&path_buf, &config);
+
     let mut very_first_loop = true;
 
     let mut state = CurrentState::new(&path_buf);
 
     loop {
-        if !very_first_loop {
-            //wait for timeout on every consequtive loop cycle
-            //it stays on top of the loop so "continue" will also wait for timeout
-            std::thread::sleep(Duration::from_secs(TIMEOUT));
-        }
+        //on every loop but the first, current_app() itself blocks for up to
+        //poll_interval_secs (on an X11 event, or a plain sleep for /proc),
+        //so decide()/UpdatePrevious still runs on schedule even when idle
+        let timeout = if very_first_loop {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs(config.poll_interval_secs)
+        };
         very_first_loop = false;
 
         if state.last_date != Local::today() {
-            println!("New day! Switching to new file");
+            info!("New day! Switching to new file");
             state = CurrentState::new(&path_buf);
-            clean_up_old_logs(&path_buf);
+            clean_up_old_logs(&path_buf, &config);
         }
 
-        let active_win_id = match get_active_win_id() {
-            Ok(win_id) => win_id,
+        let active_app = match source.current_app(timeout) {
+            Ok(app) => app,
             Err(err) => {
-                eprintln!("Error reading active window ID, {:?}", err);
+                error!("Error reading active app, {:?}", err);
                 state.last_frame = None;
                 continue;
             }
         };
+        let active_app_name = active_app.app_id;
 
-        let active_app_name = get_app_name(&active_win_id);
-        if let Err(err) = active_app_name {
-            eprintln!("Error reading active app name, {:?}", err);
-            state.last_frame = None;
-            continue;
-        };
-        let active_app_name = active_app_name.unwrap();
-
-        if should_ignore_app(&active_app_name) {
-            println!("Ignoring system app");
+        if should_ignore_app(&active_app_name, &config) {
+            info!("Ignoring system app");
             state.last_frame = None;
             continue;
         }
 
-        println!("Active app: {}", active_app_name);
+        debug!("Active app: {}", active_app_name);
 
         if !state.app_info_map.contains_key(&active_app_name) {
-            let desktop_path = get_desktop_file_path(&active_win_id);
-            if let Ok(path) = desktop_path {
-                state.app_info_map.insert(active_app_name.clone(), path);
+            if let Some(desktop_path) = active_app.desktop_file {
+                state.app_info_map.insert(active_app_name.clone(), desktop_path);
                 save_app_info(&state.app_info_map, &mut state.app_info);
             }
         }
 
-        let frame_op = decide(&state.last_frame, &active_app_name);
+        let idle = source.idle_time();
+        let frame_op = decide(&state.last_frame, &active_app_name, idle, &config);
 
         let last_frame = match frame_op {
+            FrameOperation::Close => {
+                info!("Idle for too long, closing current frame");
+                state.last_frame = None;
+                continue;
+            }
             FrameOperation::Prepare(frame) => frame,
             FrameOperation::WriteNew(frame) => {
                 let main_part = format!("{}{}{}{}", frame.name, DELIM, frame.start, DELIM);
@@ -172,17 +267,22 @@ fn main() {
 fn write_timestamp_and_flush(file: &mut File, timestamp: u64) -> usize {
     let time_str = format!("{}\n", timestamp);
     let time_str_len = time_str.as_bytes().len();
-    file.write_all(time_str.as_bytes()).unwrap_or_else(|_| eprintln!("Couldn't write activity log"));
+    file.write_all(time_str.as_bytes()).unwrap_or_else(|_| error!("Couldn't write activity log"));
     file.sync_data().expect("Couldn't flush data to file");
     time_str_len
 }
 
-fn decide(last_frame: &Option<Frame>, name: &str) -> FrameOperation {
+fn decide(last_frame: &Option<Frame>, name: &str, idle: Option<Duration>, config: &Config) -> FrameOperation {
     let timestamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap();
     let timestamp = timestamp.as_secs();
 
+    if last_frame.is_some() && idle.map_or(false, |idle| idle.as_secs() >= config.idle_threshold_secs) {
+        //away from keyboard; stop extending the frame instead of counting idle time as usage
+        return FrameOperation::Close;
+    }
+
     //try to continue previous frame
     if let Some(last_frame) = last_frame {
         if last_frame.name == name {
@@ -193,12 +293,12 @@ fn decide(last_frame: &Option<Frame>, name: &str) -> FrameOperation {
                     end: timestamp,
                 });
             }
-            if timestamp - last_frame.end < TIMEOUT * 5 {
+            if timestamp - last_frame.end < config.poll_interval_secs * config.suspend_gap_multiplier {
                 return FrameOperation::UpdatePrevious(timestamp);
             } else {
                 //computer must have been suspended, do not track that as usage
-                println!("Too much time passed between this app last logged. Creating new record");
-                println!("It was {} seconds", timestamp - last_frame.end);
+                info!("Too much time passed between this app last logged. Creating new record");
+                info!("It was {} seconds", timestamp - last_frame.end);
             }
         }
     }
@@ -211,120 +311,145 @@ fn decide(last_frame: &Option<Frame>, name: &str) -> FrameOperation {
     })
 }
 
-fn should_ignore_app(app_name: &str) -> bool {
+fn should_ignore_app(app_name: &str, config: &Config) -> bool {
     if app_name.len() == 1 {
         return true;
     }
 
-    let system_apps = &["Desktop", "unity-panel", "wingpanel"];
-    if system_apps
+    if config
+        .ignored_apps
         .iter()
-        .any(|&name| name == app_name)
+        .any(|name| name == app_name)
     {
-        println!("Ignoring system app");
+        debug!("Ignoring system app");
         return true;
     }
 
     false
 }
 
-fn get_active_win_id() -> Result<String, XpropParseError> {
-    let output = Command::new("xprop")
-        .arg("-root")
-        .arg("_NET_ACTIVE_WINDOW")
-        .output()
-        .expect("Failed to execute xprop. Do you have xprop installed?");
-    let output_str = String::from_utf8(output.stdout).map_err(|_| XpropParseError::WinId)?;
-    output_str
-        .split(' ')
-        .last()
-        .map(|word| word.to_string())
-        .ok_or(XpropParseError::WinId)
-}
-
-fn get_desktop_file_path(win_id: &str) -> Result<String, XpropParseError> {
-    let output = Command::new("xprop")
-        .arg("-id")
-        .arg(win_id)
-        .arg("_BAMF_DESKTOP_FILE")
-        .output()
-        .expect("Failed to execute xprop. Do you have xprop installed?");
-    let output_str =
-        String::from_utf8(output.stdout).map_err(|_| XpropParseError::DesktopPath)?;
-
-    let path_start = output_str.find('=');
-    let path_end = output_str.len();
-    if path_start.is_none() {
-        return Err(XpropParseError::DesktopPath);
-    }
-    let path = &output_str[path_start.unwrap() + 3..path_end - 2];
-    Ok(path.to_string())
-}
-
-fn get_app_name(win_id: &str) -> Result<String, XpropParseError> {
-    let output = Command::new("xprop")
-        .arg("-id")
-        .arg(win_id)
-        .arg("WM_CLASS")
-        .output()
-        .expect("Failed to execute xprop. Do you have xprop installed?");
-    let output_str = String::from_utf8(output.stdout).map_err(|_| XpropParseError::Class)?;
-
-    //wm class line looks like
-    //WM_CLASS(STRING) = "chromium-browser", "Chromium-browser"
-    //try to extract first parameter here
-    //so chromium-browser would app identifier
-    let name_start = output_str.find('=');
-    let name_end = output_str.find(',');
-    if name_start.is_none() || name_end.is_none() {
-        return Err(XpropParseError::Class);
-    }
-    let name = &output_str[name_start.unwrap() + 3..name_end.unwrap() - 1];
-    Ok(name.to_string())
-}
-
-fn clean_up_old_logs(path: &PathBuf) {
-    let last_allowed_date = Local::today() - chrono::Duration::days(14);
+fn clean_up_old_logs(path: &PathBuf, config: &Config) {
+    let last_allowed_date = Local::today() - chrono::Duration::days(config.retention_days);
     let last_allowed_date = last_allowed_date.naive_local();
     let file_format = format!("{}.csv", DATE_FORMAT);
 
+    let mut log_files = Vec::new();
     for file in std::fs::read_dir(path).unwrap() {
         if let Err(err) = file {
-            eprintln!("Cleanup: Error accesing filename of {}", err);
+            warn!("Cleanup: Error accesing filename of {}", err);
             continue;
         }
         let file = file.unwrap();
         let filename = file.file_name().into_string();
         if let Err(os_str_name) = filename {
-            eprintln!("Cleanup: Error reading filename of {:#?}", os_str_name);
+            warn!("Cleanup: Error reading filename of {:#?}", os_str_name);
             continue;
         }
         let filename = filename.unwrap();
         let date_parse_result = NaiveDate::parse_from_str(&filename, &file_format);
         if let Err(err) = date_parse_result {
-            eprintln!(
+            debug!(
                 "Cleanup: Not removing file {}, it is not log file. Reason: {}",
                 filename, err
             );
             continue;
         }
         let file_date = date_parse_result.unwrap();
+        log_files.push((file_date, file.path()));
+    }
 
-        if file_date < last_allowed_date {
-            if let Err(err) = fs::remove_file(file.path()) {
-                eprintln!(
-                    "Cleanup: Error removing suitable file {:#?}. Reason {}",
-                    file.path(),
-                    err
-                );
-                continue;
-            } else {
-                println!("Removed old log for {:#?}", file.path());
-            }
+    for file_path in files_to_prune(&log_files, last_allowed_date, config.keep_count) {
+        if let Err(err) = fs::remove_file(&file_path) {
+            warn!(
+                "Cleanup: Error removing suitable file {:#?}. Reason {}",
+                file_path, err
+            );
+        } else {
+            info!("Removed old log for {:#?}", file_path);
         }
     }
 }
 
+/// Decides which log files to delete: a file is only removed once it fails
+/// *both* pruning policies, i.e. it is older than `last_allowed_date` AND
+/// beyond the `keep_count` most recent files. This way whichever policy is
+/// more lenient is the one that protects the file - age alone can't wipe out
+/// a long-off user's entire history, and `keep_count` alone can't wipe out a
+/// continuously-running user's recent files.
+fn files_to_prune(
+    log_files: &[(NaiveDate, PathBuf)],
+    last_allowed_date: NaiveDate,
+    keep_count: usize,
+) -> Vec<PathBuf> {
+    let mut by_recency = log_files.to_vec();
+    //newest first, so anything past keep_count is the stale tail
+    by_recency.sort_by(|(left_date, _), (right_date, _)| right_date.cmp(left_date));
+    let stale_tail: std::collections::HashSet<_> = by_recency
+        .iter()
+        .skip(keep_count)
+        .map(|(_, path)| path.clone())
+        .collect();
+
+    log_files
+        .iter()
+        .filter(|(file_date, file_path)| *file_date < last_allowed_date && stale_tail.contains(file_path))
+        .map(|(_, file_path)| file_path.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod prune_tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn keeps_recent_files_even_if_old() {
+        // A user who left the machine off for months: every file is older
+        // than the cutoff, but there are fewer of them than keep_count, so
+        // keep_count should rescue all of them.
+        let cutoff = date(2026, 7, 1);
+        let log_files = vec![
+            (date(2026, 1, 1), PathBuf::from("Jan-01-2026.csv")),
+            (date(2026, 2, 1), PathBuf::from("Feb-01-2026.csv")),
+            (date(2026, 3, 1), PathBuf::from("Mar-01-2026.csv")),
+        ];
+        let pruned = files_to_prune(&log_files, cutoff, 10);
+        assert!(pruned.is_empty());
+    }
+
+    #[test]
+    fn prunes_only_files_both_old_and_beyond_keep_count() {
+        let cutoff = date(2026, 6, 1);
+        let log_files = vec![
+            (date(2026, 1, 1), PathBuf::from("Jan-01-2026.csv")),
+            (date(2026, 2, 1), PathBuf::from("Feb-01-2026.csv")),
+            (date(2026, 7, 1), PathBuf::from("Jul-01-2026.csv")),
+        ];
+        // keep_count=1 puts both Jan and Feb past the tail, but only
+        // Jan/Feb are also older than the cutoff, so only they are pruned.
+        let pruned = files_to_prune(&log_files, cutoff, 1);
+        assert_eq!(pruned.len(), 2);
+        assert!(pruned.contains(&PathBuf::from("Jan-01-2026.csv")));
+        assert!(pruned.contains(&PathBuf::from("Feb-01-2026.csv")));
+    }
+
+    #[test]
+    fn keeps_files_within_age_cutoff_even_if_beyond_keep_count() {
+        let cutoff = date(2020, 1, 1);
+        let log_files = vec![
+            (date(2026, 1, 1), PathBuf::from("a.csv")),
+            (date(2026, 2, 1), PathBuf::from("b.csv")),
+        ];
+        // keep_count=0 puts every file past the tail, but none are older
+        // than the (very old) cutoff, so nothing is pruned.
+        let pruned = files_to_prune(&log_files, cutoff, 0);
+        assert!(pruned.is_empty());
+    }
+}
+
 fn read_desktop_paths(file: &mut File) -> std::io::Result<HashMap<String, String>> {
     let mut text = String::new();
     file.read_to_string(&mut text)?;
@@ -333,7 +458,7 @@ fn read_desktop_paths(file: &mut File) -> std::io::Result<HashMap<String, String
     for line in text.lines() {
         let words: Vec<&str> = line.split(DELIM).collect();
         if words.len() != 2 {
-            eprintln!("Skipping line from desktop paths file");
+            warn!("Skipping line from desktop paths file");
             continue;
         }
         map.insert(words[0].to_string(), words[1].to_string());
@@ -345,6 +470,6 @@ fn save_app_info(map: &HashMap<String, String>, file: &mut File) {
     let _ = file.seek(SeekFrom::Start(0)).unwrap();
     for (key, value) in map {
         let line = format!("{}{}{}\n", key, DELIM, value);
-        file.write_all(line.as_bytes()).unwrap_or_else(|_| eprintln!("Couldn't save desktop paths file"));;
+        file.write_all(line.as_bytes()).unwrap_or_else(|_| error!("Couldn't save desktop paths file"));
     }
 }
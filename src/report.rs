@@ -0,0 +1,254 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::{Serialize, Serializer};
+
+use crate::config::Config;
+use crate::{DATE_FORMAT, DELIM};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppUsage {
+    app_id: String,
+    friendly_name: String,
+    total_secs: u64,
+    #[serde(serialize_with = "serialize_by_day")]
+    by_day: BTreeMap<NaiveDate, u64>,
+}
+
+/// Serializes `by_day` with `DATE_FORMAT` string keys, preserving the
+/// chronological order of the underlying `BTreeMap<NaiveDate, _>`.
+fn serialize_by_day<S>(by_day: &BTreeMap<NaiveDate, u64>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    use serde::ser::SerializeMap;
+    let mut map = serializer.serialize_map(Some(by_day.len()))?;
+    for (day, secs) in by_day {
+        map.serialize_entry(&day.format(DATE_FORMAT).to_string(), secs)?;
+    }
+    map.end()
+}
+
+/// Aggregates the daily CSV logs into per-app usage totals and prints them
+/// in the requested format.
+pub fn run(
+    config: &Config,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    app_filter: Option<&str>,
+    format: ReportFormat,
+) {
+    let friendly_names = read_friendly_names(config);
+    let mut usage: HashMap<String, AppUsage> = HashMap::new();
+
+    let file_format = format!("{}.csv", DATE_FORMAT);
+    let entries = match fs::read_dir(&config.data_dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("Report: couldn't read {:#?}: {}", config.data_dir, err);
+            return;
+        }
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let filename = match entry.file_name().into_string() {
+            Ok(filename) => filename,
+            Err(_) => continue,
+        };
+        let date = match NaiveDate::parse_from_str(&filename, &file_format) {
+            Ok(date) => date,
+            Err(_) => continue,
+        };
+        if since.map_or(false, |since| date < since) {
+            continue;
+        }
+        if until.map_or(false, |until| date > until) {
+            continue;
+        }
+
+        let text = fs::read_to_string(entry.path()).unwrap_or_default();
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split(DELIM).collect();
+            if fields.len() != 3 {
+                continue;
+            }
+            let app_id = fields[0];
+            if let Some(filter) = app_filter {
+                if app_id != filter {
+                    continue;
+                }
+            }
+            let start: u64 = match fields[1].parse() {
+                Ok(start) => start,
+                Err(_) => continue,
+            };
+            let end: u64 = match fields[2].parse() {
+                Ok(end) => end,
+                Err(_) => continue,
+            };
+            let duration = end.saturating_sub(start);
+
+            let app_usage = usage.entry(app_id.to_string()).or_insert_with(|| AppUsage {
+                app_id: app_id.to_string(),
+                friendly_name: friendly_names
+                    .get(app_id)
+                    .cloned()
+                    .unwrap_or_else(|| app_id.to_string()),
+                total_secs: 0,
+                by_day: BTreeMap::new(),
+            });
+            app_usage.total_secs += duration;
+            *app_usage.by_day.entry(date).or_insert(0) += duration;
+        }
+    }
+
+    let mut rows: Vec<AppUsage> = usage.into_values().collect();
+    rows.sort_by(|left, right| right.total_secs.cmp(&left.total_secs));
+
+    match format {
+        ReportFormat::Text => print_text(&rows),
+        ReportFormat::Json => print_json(&rows),
+        ReportFormat::Csv => print_csv(&rows),
+    }
+}
+
+fn read_friendly_names(config: &Config) -> HashMap<String, String> {
+    let path = config.data_dir.join("app-names.csv");
+    let text = fs::read_to_string(&path).unwrap_or_default();
+
+    let mut names = HashMap::new();
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split(DELIM).collect();
+        if fields.len() != 2 {
+            continue;
+        }
+        let app_id = fields[0].to_string();
+        let friendly = desktop_entry_name(fields[1]).unwrap_or_else(|| app_id.clone());
+        names.insert(app_id, friendly);
+    }
+    names
+}
+
+fn desktop_entry_name(desktop_path: &str) -> Option<String> {
+    let text = fs::read_to_string(desktop_path).ok()?;
+    text.lines()
+        .find(|line| line.starts_with("Name="))
+        .map(|line| line.trim_start_matches("Name=").to_string())
+}
+
+fn format_duration(total_secs: u64) -> String {
+    format!("{}h {}m", total_secs / 3600, (total_secs % 3600) / 60)
+}
+
+fn print_text(rows: &[AppUsage]) {
+    for app in rows {
+        println!("{:<30} {}", app.friendly_name, format_duration(app.total_secs));
+        for (day, secs) in &app.by_day {
+            println!("  {:<10} {}", day.format(DATE_FORMAT), format_duration(*secs));
+        }
+    }
+}
+
+fn print_json(rows: &[AppUsage]) {
+    match serde_json::to_string_pretty(rows) {
+        Ok(json) => println!("{}", json),
+        Err(err) => eprintln!("Report: couldn't serialize to JSON: {}", err),
+    }
+}
+
+/// Quotes a CSV field per RFC4180: wraps in `"` and doubles any embedded `"`
+/// whenever the field contains a comma, quote, or newline that would
+/// otherwise be read as a field/row boundary.
+fn csv_field(value: &str) -> String {
+    if value.contains(['"', ',', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn print_csv(rows: &[AppUsage]) {
+    println!("app_id,friendly_name,date,seconds");
+    for app in rows {
+        for (day, secs) in &app.by_day {
+            println!(
+                "{},{},{},{}",
+                csv_field(&app.app_id),
+                csv_field(&app.friendly_name),
+                day.format(DATE_FORMAT),
+                secs
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn format_duration_splits_hours_and_minutes() {
+        assert_eq!(format_duration(3661), "1h 1m");
+        assert_eq!(format_duration(59), "0h 0m");
+    }
+
+    #[test]
+    fn by_day_iterates_chronologically_across_months_and_years() {
+        // Regression for the lexicographic "%b-%d-%Y" string-key bug:
+        // February must sort after January even though 'F' < 'J'.
+        let mut by_day = BTreeMap::new();
+        by_day.insert(date(2026, 2, 1), 10);
+        by_day.insert(date(2026, 1, 15), 20);
+        by_day.insert(date(2025, 12, 31), 30);
+
+        let ordered: Vec<NaiveDate> = by_day.keys().copied().collect();
+        assert_eq!(
+            ordered,
+            vec![date(2025, 12, 31), date(2026, 1, 15), date(2026, 2, 1)]
+        );
+    }
+
+    #[test]
+    fn serialize_by_day_emits_date_format_keys_in_order() {
+        let mut by_day = BTreeMap::new();
+        by_day.insert(date(2026, 2, 1), 10u64);
+        by_day.insert(date(2026, 1, 15), 20u64);
+
+        let usage = AppUsage {
+            app_id: "firefox".to_string(),
+            friendly_name: "Firefox".to_string(),
+            total_secs: 30,
+            by_day,
+        };
+
+        let json = serde_json::to_string(&usage).unwrap();
+        let jan_pos = json.find("Jan-15-2026").unwrap();
+        let feb_pos = json.find("Feb-01-2026").unwrap();
+        assert!(jan_pos < feb_pos, "expected Jan before Feb in: {}", json);
+    }
+
+    #[test]
+    fn csv_field_quotes_commas_and_escapes_quotes() {
+        assert_eq!(csv_field("Firefox"), "Firefox");
+        assert_eq!(csv_field("Games, Inc"), "\"Games, Inc\"");
+        assert_eq!(csv_field("Say \"Hi\""), "\"Say \"\"Hi\"\"\"");
+        assert_eq!(csv_field("multi\nline"), "\"multi\nline\"");
+    }
+}